@@ -0,0 +1,110 @@
+//! Progress reporting for commands that run over every .easl file in a
+//! directory. A progress bar tracks the run while it's going, and a summary
+//! table of every file is printed once it's done.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+struct FileResult {
+  file: PathBuf,
+  succeeded: bool,
+  duration: Duration,
+  /// Size of the file that was written, if any.
+  output_bytes: Option<usize>,
+}
+
+pub struct BatchProgress {
+  bar: ProgressBar,
+  results: Vec<FileResult>,
+}
+
+impl BatchProgress {
+  pub fn new(verb: &'static str, file_count: usize) -> Self {
+    // indicatif hides the bar on its own when stderr isn't a terminal.
+    let bar = ProgressBar::new(file_count as u64);
+    bar.set_style(
+      ProgressStyle::with_template(
+        "{prefix} [{bar:30}] {pos}/{len} (ETA {eta}) {wide_msg}",
+      )
+      .unwrap_or_else(|_| ProgressStyle::default_bar())
+      .progress_chars("=> "),
+    );
+    bar.set_prefix(verb);
+    Self {
+      bar,
+      results: Vec::new(),
+    }
+  }
+
+  /// Runs `step` on a file, recording how long it took and the size of any
+  /// output it reports. Errors are printed above the progress bar. Returns
+  /// the step's value if it succeeded.
+  pub fn run<T>(
+    &mut self,
+    file: &Path,
+    step: impl FnOnce() -> Result<(T, Option<usize>), String>,
+  ) -> Option<T> {
+    self.bar.set_message(file.display().to_string());
+    let start = Instant::now();
+    let result = step();
+    let duration = start.elapsed();
+    self.bar.inc(1);
+
+    let (value, output_bytes) = match result {
+      Ok((value, output_bytes)) => (Some(value), output_bytes),
+      Err(e) => {
+        self
+          .bar
+          .suspend(|| eprintln!("{}\n{}\n", file.display(), e));
+        (None, None)
+      }
+    };
+    self.results.push(FileResult {
+      file: file.to_path_buf(),
+      succeeded: value.is_some(),
+      duration,
+      output_bytes,
+    });
+    value
+  }
+
+  /// Clears the progress bar and prints the summary table, returning the
+  /// number of files that failed.
+  pub fn finish(self) -> usize {
+    self.bar.finish_and_clear();
+
+    let labels = self
+      .results
+      .iter()
+      .map(|result| result.file.display().to_string())
+      .collect::<Vec<_>>();
+    let label_width = labels
+      .iter()
+      .map(String::len)
+      .chain(["file".len()])
+      .max()
+      .unwrap_or_default();
+    println!(
+      "{:<label_width$} {:>6} {:>10} {:>12}",
+      "file", "status", "time", "output bytes"
+    );
+    for (label, result) in labels.iter().zip(&self.results) {
+      println!(
+        "{label:<label_width$} {:>6} {:>10} {:>12}",
+        if result.succeeded { "ok" } else { "failed" },
+        format!("{:.1?}", result.duration),
+        result
+          .output_bytes
+          .map_or_else(|| "-".to_string(), |bytes| bytes.to_string())
+      );
+    }
+
+    self
+      .results
+      .iter()
+      .filter(|result| !result.succeeded)
+      .count()
+  }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::toplevel::top_level_forms;
+
+#[derive(Serialize)]
+struct BundleEntry {
+  name: String,
+  stage: String,
+  metadata: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct BundleVar {
+  name: String,
+  #[serde(rename = "type")]
+  ty: Option<String>,
+  metadata: BTreeMap<String, String>,
+}
+
+/// A compiled shader and the information an engine needs to load it, written
+/// as a single `.easlpkg.json` file.
+#[derive(Serialize)]
+struct Bundle {
+  /// SHA-256 of the .easl source the bundle was compiled from.
+  hash: String,
+  wgsl: String,
+  entries: Vec<BundleEntry>,
+  vars: Vec<BundleVar>,
+}
+
+pub fn bundle_json(easl_source: &str, wgsl: String) -> Result<String, String> {
+  let forms = top_level_forms(easl_source);
+  let metadata_map = |metadata: &[(String, String)]| {
+    metadata.iter().cloned().collect::<BTreeMap<_, _>>()
+  };
+  let bundle = Bundle {
+    hash: Sha256::digest(easl_source.as_bytes())
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect(),
+    wgsl,
+    entries: forms
+      .iter()
+      .filter_map(|form| {
+        Some(BundleEntry {
+          name: form.name.clone(),
+          stage: form.stage()?.to_string(),
+          metadata: metadata_map(&form.metadata),
+        })
+      })
+      .collect(),
+    vars: forms
+      .iter()
+      .filter(|form| form.kind == "var")
+      .map(|form| BundleVar {
+        name: form.name.clone(),
+        ty: form.declared_type.clone(),
+        metadata: metadata_map(&form.metadata),
+      })
+      .collect(),
+  };
+  serde_json::to_string_pretty(&bundle)
+    .map_err(|e| format!("Error: Failed to serialize bundle\n{e}"))
+}
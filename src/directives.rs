@@ -0,0 +1,156 @@
+//! Formatter directives written as comments in .easl source:
+//!
+//! ```text
+//! ; easl-format: off
+//! (def lookup-table: [f32 4] [0.  0.5
+//!                             0.5 1.])
+//! ; easl-format: on
+//! ```
+//!
+//! Top-level forms that start between an `off` and the next `on` (or the
+//! end of the file) keep their original text when the file is formatted.
+
+use std::ops::Range;
+
+use easl::format_easl_source;
+
+use crate::toplevel::top_level_forms;
+
+fn directive(line: &str) -> Option<&str> {
+  line
+    .trim()
+    .strip_prefix(';')?
+    .trim_start_matches(';')
+    .trim()
+    .strip_prefix("easl-format:")
+    .map(str::trim)
+}
+
+fn unformatted_regions(source: &str) -> Vec<Range<usize>> {
+  let mut regions = Vec::new();
+  let mut region_start = None;
+  let mut offset = 0;
+  for line in source.split_inclusive('\n') {
+    match directive(line) {
+      Some("off") if region_start.is_none() => region_start = Some(offset),
+      Some("on") => {
+        if let Some(start) = region_start.take() {
+          regions.push(start..offset);
+        }
+      }
+      _ => {}
+    }
+    offset += line.len();
+  }
+  if let Some(start) = region_start {
+    regions.push(start..source.len());
+  }
+  regions
+}
+
+/// Formats `source`, leaving the forms inside `easl-format: off` regions as
+/// they were written.
+pub fn format_with_directives(source: &str) -> Result<String, String> {
+  let regions = unformatted_regions(source);
+  if regions.is_empty() {
+    return Ok(format_easl_source(source));
+  }
+
+  let original_forms = top_level_forms(source);
+  let is_protected = |span: &Range<usize>| {
+    regions.iter().any(|region| region.contains(&span.start))
+  };
+  if original_forms.iter().all(|form| is_protected(&form.span)) {
+    // Formatting is off for the whole file.
+    return Ok(source.to_string());
+  }
+
+  let mut formatted = format_easl_source(source);
+  let formatted_forms = top_level_forms(&formatted);
+  if original_forms.len() != formatted_forms.len() {
+    return Err(
+      "Error: Couldn't match top-level forms before and after formatting, \
+       so `easl-format: off` regions can't be preserved"
+        .to_string(),
+    );
+  }
+
+  // Splice from the end of the file backwards, so the spans of earlier
+  // forms in the formatted text stay valid.
+  for (original, formatted_form) in
+    original_forms.iter().zip(&formatted_forms).rev()
+  {
+    if is_protected(&original.span) {
+      formatted.replace_range(
+        formatted_form.span.clone(),
+        &source[original.span.clone()],
+      );
+    }
+  }
+  Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn directive_comment_styles() {
+    assert_eq!(directive("; easl-format: off"), Some("off"));
+    assert_eq!(directive(";; easl-format: on\n"), Some("on"));
+    assert_eq!(directive("  ;;;easl-format:off"), Some("off"));
+    assert_eq!(directive("; some other comment"), None);
+    assert_eq!(directive("(def a 1) ; easl-format: off"), None);
+  }
+
+  #[test]
+  fn off_without_on_runs_to_end_of_file() {
+    let source = "(def a 1)\n; easl-format: off\n(def b 2)\n";
+    let regions = unformatted_regions(source);
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0], 10..source.len());
+  }
+
+  #[test]
+  fn on_closes_region() {
+    let source =
+      ";; easl-format: off\n(def a 1)\n;; easl-format: on\n(def b 2)\n";
+    let regions = unformatted_regions(source);
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0], 0..30);
+  }
+
+  #[test]
+  fn whole_file_off_is_unchanged() {
+    let source = "; easl-format: off\n(def  a   1)\n\n\n(def  b\n  2)";
+    assert_eq!(format_with_directives(source).unwrap(), source);
+  }
+
+  #[test]
+  fn directive_inside_multi_line_form() {
+    // The directive is inside `f`, so `f` started before the region and is
+    // formatted, while `b` starts inside it and is kept as written.
+    let source = "\
+(defn f []
+  ; easl-format: off
+  (+ 1 2))
+(def  b   2)
+; easl-format: on
+(def c 3)
+";
+    let regions = unformatted_regions(source);
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0], 11..source.find("; easl-format: on").unwrap());
+    let formatted = format_with_directives(source).unwrap();
+    assert!(formatted.contains("(def  b   2)"), "{formatted}");
+  }
+
+  #[test]
+  fn no_directives_formats_normally() {
+    let source = "(def  a   1)\n";
+    assert_eq!(
+      format_with_directives(source).unwrap(),
+      format_easl_source(source)
+    );
+  }
+}
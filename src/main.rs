@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 #[cfg(feature = "interpreter")]
 use easl::compiler::builtins::built_in_macros;
 #[cfg(feature = "interpreter")]
@@ -12,14 +12,41 @@ use easl::interpreter::{
 use easl::parse::parse_easl_without_comments;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use easl::{compile_easl_source_to_wgsl, format_easl_source};
+use easl::compile_easl_source_to_wgsl;
+use batch::BatchProgress;
+use bundle::bundle_json;
+use directives::format_with_directives;
+use overrides::parse_def_override;
+#[cfg(feature = "interpreter")]
+use overrides::apply_def_overrides;
+use project::{check_project_consistency, find_unused_definitions};
+use remote::{fetch_remote_source, is_url, url_file_name};
+use stats::print_stats;
+use timings::record_build_timings;
+use watch::watch_project;
 use notify::{
   Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
 };
 use std::collections::HashMap;
 use std::fs;
+#[cfg(feature = "interpreter")]
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "interpreter")]
+mod playlist;
+mod batch;
+mod bundle;
+mod directives;
+mod overrides;
+mod project;
+mod remote;
+mod stats;
+mod timings;
+mod toplevel;
+mod watch;
 
 #[derive(Parser)]
 #[command(name = "easl")]
@@ -29,25 +56,80 @@ struct Cli {
   command: Command,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum Emit {
+  /// A .wgsl file
+  Wgsl,
+  /// A .easlpkg.json file containing the WGSL along with entry point and
+  /// var metadata
+  Bundle,
+}
+
+impl Emit {
+  fn extension(self) -> &'static str {
+    match self {
+      Emit::Wgsl => "wgsl",
+      Emit::Bundle => "easlpkg.json",
+    }
+  }
+}
+
 #[derive(Subcommand)]
 enum Command {
   /// Compile a .easl file to .wgsl
   Compile {
-    /// Path of the .easl file or directory to compile
+    /// Path or http(s) URL of the .easl file, or directory to compile
     input: PathBuf,
 
     /// Output file or directory, defaults to input file with .wgsl extension
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Kind of output to produce
+    #[arg(long, value_enum, default_value = "wgsl")]
+    emit: Emit,
+
     /// Watch for file changes and recompile automatically
     #[arg(short, long)]
     watch: bool,
+
+    /// Treat a directory as one project and check that its files agree on
+    /// shared definitions
+    #[arg(long)]
+    project: bool,
+
+    /// JSON file to record compile times in, warning when a file compiles
+    /// much slower than it has historically
+    #[arg(long, value_name = "FILE")]
+    timings_history: Option<PathBuf>,
   },
   /// Typecheck a .easl file without comiling
   Check {
     /// Path of the .easl file or directory to check
     input: PathBuf,
+
+    /// Treat a directory as one project and check that its files agree on
+    /// shared definitions
+    #[arg(long)]
+    project: bool,
+  },
+  /// Report problems in .easl files that the compiler doesn't catch
+  Lint {
+    /// Path of the .easl file or directory to lint
+    input: PathBuf,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    json: bool,
+  },
+  /// Print metrics about .easl files
+  Stats {
+    /// Path of the .easl file or directory to report on
+    input: PathBuf,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    json: bool,
   },
   /// Format a .easl file
   Format {
@@ -58,9 +140,17 @@ enum Command {
     #[arg(short, long)]
     output: Option<PathBuf>,
   },
+  /// Rerun format, check, and compile over a project whenever it changes,
+  /// as configured by an easl.toml file
+  Watch {
+    /// Path of the project's config file
+    #[arg(short, long, default_value = "easl.toml")]
+    config: PathBuf,
+  },
   /// Run a .easl file as a standalone application
   Run {
-    /// Path of the .easl file to run
+    /// Path or http(s) URL of the .easl file to run, or a directory to play
+    /// as a playlist
     input: PathBuf,
 
     #[arg(
@@ -74,9 +164,42 @@ enum Command {
     /// Watch for file changes and hot-reload the shader
     #[arg(short, long)]
     watch: bool,
+
+    /// Seconds to show each shader for when running a directory
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval)]
+    interval: Option<Duration>,
+
+    /// Shuffle the playlist order when running a directory
+    #[arg(long)]
+    shuffle: bool,
+
+    /// Write the compiled WGSL to this path each time the shader compiles
+    #[arg(long, value_name = "PATH")]
+    dump_wgsl: Option<PathBuf>,
+
+    /// Override the value of a top-level `def`, e.g. `--set iterations=128`
+    #[arg(
+      long = "set",
+      value_name = "NAME=VALUE",
+      value_parser = parse_def_override
+    )]
+    set: Vec<(String, String)>,
   },
 }
 
+/// Parses a playlist interval given in seconds, which must be positive.
+fn parse_interval(arg: &str) -> Result<Duration, String> {
+  let secs = arg
+    .parse::<f64>()
+    .map_err(|e| format!("expected a number of seconds, got `{arg}`\n{e}"))?;
+  if secs <= 0.0 {
+    return Err(format!(
+      "expected a positive number of seconds, got `{arg}`"
+    ));
+  }
+  Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+}
+
 fn read_source(input: &PathBuf) -> Result<String, String> {
   fs::read_to_string(&input).map_err(|e| {
     format!(
@@ -148,40 +271,65 @@ fn find_easl_files(dir: &PathBuf) -> Result<Vec<PathBuf>, String> {
   Ok(easl_files)
 }
 
+struct CompiledFile {
+  output_path: PathBuf,
+  output_bytes: usize,
+  compile_time: Duration,
+}
+
+fn write_compiled_file(
+  input: &PathBuf,
+  output: Option<PathBuf>,
+  emit: Emit,
+) -> Result<CompiledFile, String> {
+  let easl_source = read_source(input)?;
+
+  let start = Instant::now();
+  let compile_result = try_compile_easl(&easl_source);
+  let compile_time = start.elapsed();
+  let wgsl = compile_result?;
+  let output_path = output.unwrap_or_else(|| {
+    let mut output_path = input.clone();
+    output_path.set_extension(emit.extension());
+    output_path
+  });
+
+  let output_content = match emit {
+    Emit::Wgsl => wgsl,
+    Emit::Bundle => bundle_json(&easl_source, wgsl)?,
+  };
+
+  fs::write(&output_path, &output_content).map_err(|e| {
+    format!(
+      "Error: Failed to write output file {}\n{}",
+      output_path.display(),
+      e
+    )
+  })?;
+
+  Ok(CompiledFile {
+    output_path,
+    output_bytes: output_content.len(),
+    compile_time,
+  })
+}
+
 fn compile_single_file(
   input: PathBuf,
   output: Option<PathBuf>,
-) -> Result<(), String> {
-  let easl_source = read_source(&input)?;
-
+  emit: Emit,
+) -> Result<Duration, String> {
   println!("Compiling {}...", input.display());
-  match try_compile_easl(&easl_source) {
-    Ok(wgsl) => {
-      let output_path = output.unwrap_or_else(|| {
-        let mut output_path = input.clone();
-        output_path.set_extension("wgsl");
-        output_path
-      });
-
-      fs::write(&output_path, wgsl).map_err(|e| {
-        format!(
-          "Error: Failed to write output file {}\n{}",
-          output_path.display(),
-          e
-        )
-      })?;
-
-      println!("Finished: {}", output_path.display());
-      Ok(())
-    }
-    Err(e) => Err(e),
-  }
+  let compiled = write_compiled_file(&input, output, emit)?;
+  println!("Finished: {}", compiled.output_path.display());
+  Ok(compiled.compile_time)
 }
 
 fn get_output_path_for_file(
   file: &Path,
   input_base: &Path,
   output_base: &Option<PathBuf>,
+  emit: Emit,
 ) -> Result<PathBuf, String> {
   if let Some(output_dir) = output_base {
     if input_base.is_dir() {
@@ -196,7 +344,7 @@ fn get_output_path_for_file(
 
       // Construct output path with same relative structure
       let mut out_path = output_dir.join(relative_path);
-      out_path.set_extension("wgsl");
+      out_path.set_extension(emit.extension());
 
       // Create parent directories if they don't exist
       if let Some(parent) = out_path.parent() {
@@ -217,7 +365,7 @@ fn get_output_path_for_file(
   } else {
     // No output specified, use default
     let mut output_path = file.to_path_buf();
-    output_path.set_extension("wgsl");
+    output_path.set_extension(emit.extension());
     Ok(output_path)
   }
 }
@@ -226,10 +374,28 @@ fn compile_file(
   input: PathBuf,
   output: Option<PathBuf>,
   watch: bool,
+  project: bool,
+  emit: Emit,
+  timings_history: Option<PathBuf>,
 ) -> Result<(), String> {
+  let (input, output) = if is_url(&input) {
+    if watch {
+      return Err("Error: --watch is not supported for URL inputs".to_string());
+    }
+    // Remote sources are compiled into the current directory by default,
+    // rather than next to the cached download.
+    let output = output.unwrap_or_else(|| {
+      PathBuf::from(url_file_name(&input.to_string_lossy()))
+        .with_extension(emit.extension())
+    });
+    (fetch_remote_source(&input)?, Some(output))
+  } else {
+    (input, output)
+  };
+
   if watch {
     // Initial compilation
-    compile_once(&input, &output)?;
+    compile_once(&input, &output, project, emit, &timings_history)?;
 
     // Build initial content cache
     let mut file_contents: HashMap<PathBuf, String> = HashMap::new();
@@ -292,7 +458,7 @@ fn compile_file(
 
               println!("\n{} changed, recompiling...", path.display());
               let output_path =
-                match get_output_path_for_file(&path, &input, &output) {
+                match get_output_path_for_file(&path, &input, &output, emit) {
                   Ok(p) => Some(p),
                   Err(e) => {
                     eprintln!("{}", e);
@@ -300,7 +466,25 @@ fn compile_file(
                   }
                 };
 
-              if let Err(e) = compile_single_file(path.clone(), output_path) {
+              match compile_single_file(path.clone(), output_path, emit) {
+                Ok(compile_time) => {
+                  if let Some(history_path) = &timings_history
+                    && let Err(e) = record_build_timings(
+                      history_path,
+                      &[(path.clone(), compile_time)],
+                    )
+                  {
+                    eprintln!("{}", e);
+                  }
+                }
+                Err(e) => eprintln!("{}", e),
+              }
+
+              if project
+                && input.is_dir()
+                && let Err(e) = find_easl_files(&input)
+                  .and_then(|files| check_project_consistency(&files))
+              {
                 eprintln!("{}", e);
               }
 
@@ -317,13 +501,16 @@ fn compile_file(
       }
     }
   } else {
-    compile_once(&input, &output)
+    compile_once(&input, &output, project, emit, &timings_history)
   }
 }
 
 fn compile_once(
   input: &PathBuf,
   output: &Option<PathBuf>,
+  project: bool,
+  emit: Emit,
+  timings_history: &Option<PathBuf>,
 ) -> Result<(), String> {
   if input.is_dir() {
     // Compile all .easl files in the directory recursively
@@ -342,25 +529,28 @@ fn compile_once(
       input.display()
     );
 
-    let mut failed = Vec::new();
+    let mut progress = BatchProgress::new("Compiling", easl_files.len());
+    let mut timings = Vec::new();
     for file in &easl_files {
-      let output_path = match get_output_path_for_file(file, input, output) {
-        Ok(p) => Some(p),
-        Err(e) => {
-          eprintln!("{}", e);
-          failed.push(file);
-          continue;
-        }
-      };
-
-      if let Err(e) = compile_single_file(file.clone(), output_path) {
-        eprintln!("{}", e);
-        failed.push(file);
+      let compiled = progress.run(file, || {
+        let output_path = get_output_path_for_file(file, input, output, emit)?;
+        let compiled = write_compiled_file(file, Some(output_path), emit)?;
+        Ok((compiled.compile_time, Some(compiled.output_bytes)))
+      });
+      if let Some(compile_time) = compiled {
+        timings.push((file.clone(), compile_time));
       }
     }
+    let failed = progress.finish();
 
-    if !failed.is_empty() {
-      Err(format!("\nFailed to compile {} file(s)", failed.len()))
+    if let Some(history_path) = timings_history {
+      record_build_timings(history_path, &timings)?;
+    }
+
+    if failed > 0 {
+      Err(format!("\nFailed to compile {} file(s)", failed))
+    } else if project {
+      check_project_consistency(&easl_files)
     } else {
       Ok(())
     }
@@ -371,7 +561,11 @@ fn compile_once(
     } else {
       None
     };
-    compile_single_file(input.clone(), output_path)
+    let compile_time = compile_single_file(input.clone(), output_path, emit)?;
+    if let Some(history_path) = timings_history {
+      record_build_timings(history_path, &[(input.clone(), compile_time)])?;
+    }
+    Ok(())
   }
 }
 
@@ -390,7 +584,7 @@ fn check_single_file(input: PathBuf) -> Result<(), String> {
   }
 }
 
-fn check_file(input: PathBuf) -> Result<(), String> {
+fn check_file(input: PathBuf, project: bool) -> Result<(), String> {
   if input.is_dir() {
     // Check all .easl files in the directory recursively
     let easl_files = find_easl_files(&input)?;
@@ -408,15 +602,19 @@ fn check_file(input: PathBuf) -> Result<(), String> {
       input.display()
     );
 
-    let mut failed = Vec::new();
+    let mut progress = BatchProgress::new("Typechecking", easl_files.len());
     for file in &easl_files {
-      if let Err(_) = check_single_file(file.clone()) {
-        failed.push(file);
-      }
+      progress.run(file, || {
+        try_compile_easl(&read_source(file)?)?;
+        Ok(((), None))
+      });
     }
+    let failed = progress.finish();
 
-    if !failed.is_empty() {
-      Err(format!("\nFailed to typecheck {} file(s)", failed.len()))
+    if failed > 0 {
+      Err(format!("\nFailed to typecheck {} file(s)", failed))
+    } else if project {
+      check_project_consistency(&easl_files)
     } else {
       Ok(())
     }
@@ -426,21 +624,93 @@ fn check_file(input: PathBuf) -> Result<(), String> {
   }
 }
 
-fn format_single_file(
-  input: PathBuf,
+fn lint_file(input: PathBuf, json: bool) -> Result<(), String> {
+  let easl_files = if input.is_dir() {
+    find_easl_files(&input)?
+  } else {
+    vec![input.clone()]
+  };
+
+  if easl_files.is_empty() {
+    return Err(format!(
+      "No .easl files found in directory {}",
+      input.display()
+    ));
+  }
+
+  let unused_definitions = find_unused_definitions(&easl_files)?;
+
+  if json {
+    let report = serde_json::json!({ "unused": unused_definitions });
+    println!(
+      "{}",
+      serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Error: Failed to serialize lints\n{e}"))?
+    );
+  } else {
+    for definition in &unused_definitions {
+      println!(
+        "{}:{}: unused {} `{}`",
+        definition.file.display(),
+        definition.line,
+        definition.kind,
+        definition.name
+      );
+    }
+  }
+
+  if unused_definitions.is_empty() {
+    Ok(())
+  } else {
+    Err(format!(
+      "\nFound {} unused definition(s)",
+      unused_definitions.len()
+    ))
+  }
+}
+
+fn stats_file(input: PathBuf, json: bool) -> Result<(), String> {
+  let easl_files = if input.is_dir() {
+    find_easl_files(&input)?
+  } else {
+    vec![input.clone()]
+  };
+
+  if easl_files.is_empty() {
+    return Err(format!(
+      "No .easl files found in directory {}",
+      input.display()
+    ));
+  }
+
+  print_stats(&easl_files, json)
+}
+
+/// Formats `input` into `output`, or in place, returning the path written
+/// and the size of the formatted source.
+fn write_formatted_file(
+  input: &PathBuf,
   output: Option<PathBuf>,
-) -> Result<(), String> {
-  let easl_source = read_source(&input)?;
-  println!("Formatting {}...", input.display());
-  let formatted = format_easl_source(&easl_source);
+) -> Result<(PathBuf, usize), String> {
+  let easl_source = read_source(input)?;
+  let formatted = format_with_directives(&easl_source)?;
   let output_path = output.unwrap_or_else(|| input.clone());
-  fs::write(&output_path, formatted).map_err(|e| {
+  fs::write(&output_path, &formatted).map_err(|e| {
     format!(
       "Error: Failed to write output file {}\n{}",
       output_path.display(),
       e
     )
   })?;
+  Ok((output_path, formatted.len()))
+}
+
+fn format_single_file(
+  input: PathBuf,
+  output: Option<PathBuf>,
+) -> Result<(), String> {
+  println!("Formatting {}...", input.display());
+  let (output_path, _) = write_formatted_file(&input, output)?;
   println!("Formatted: {}", output_path.display());
   Ok(())
 }
@@ -463,7 +733,7 @@ fn format_file(input: PathBuf, output: Option<PathBuf>) -> Result<(), String> {
       input.display()
     );
 
-    let mut failed = Vec::new();
+    let mut progress = BatchProgress::new("Formatting", easl_files.len());
     for file in &easl_files {
       let output_path = if let Some(ref output_dir) = output {
         // Calculate relative path from input directory
@@ -494,14 +764,15 @@ fn format_file(input: PathBuf, output: Option<PathBuf>) -> Result<(), String> {
         None
       };
 
-      if let Err(e) = format_single_file(file.clone(), output_path) {
-        eprintln!("{}", e);
-        failed.push(file);
-      }
+      progress.run(file, || {
+        let (_, formatted_bytes) = write_formatted_file(file, output_path)?;
+        Ok(((), Some(formatted_bytes)))
+      });
     }
+    let failed = progress.finish();
 
-    if !failed.is_empty() {
-      Err(format!("\nFailed to format {} file(s)", failed.len()))
+    if failed > 0 {
+      Err(format!("\nFailed to format {} file(s)", failed))
     } else {
       Ok(())
     }
@@ -511,13 +782,61 @@ fn format_file(input: PathBuf, output: Option<PathBuf>) -> Result<(), String> {
   }
 }
 
+#[cfg(feature = "interpreter")]
+fn dump_wgsl(easl_source: &str, path: &Path) {
+  // Dumping is a debugging aid, so failures are reported without
+  // interrupting the running program.
+  match try_compile_easl(easl_source) {
+    Ok(wgsl) => match fs::write(path, wgsl) {
+      Ok(()) => println!("Wrote WGSL to {}", path.display()),
+      Err(e) => eprintln!(
+        "Error: Failed to write output file {}\n{}",
+        path.display(),
+        e
+      ),
+    },
+    Err(e) => eprintln!("Failed to dump WGSL:\n{e}"),
+  }
+}
+
 #[cfg(feature = "interpreter")]
 fn run_file(
   input: PathBuf,
   entry: Option<String>,
   watch: bool,
+  interval: Option<Duration>,
+  shuffle: bool,
+  dump_wgsl_path: Option<PathBuf>,
+  def_overrides: Vec<(String, String)>,
 ) -> Result<(), String> {
-  if watch {
+  let input = if is_url(&input) {
+    if watch {
+      return Err("Error: --watch is not supported for URL inputs".to_string());
+    }
+    fetch_remote_source(&input)?
+  } else {
+    input
+  };
+
+  if input.is_dir() {
+    if watch {
+      return Err(
+        "Error: --watch is not supported when running a directory".to_string(),
+      );
+    }
+    if dump_wgsl_path.is_some() {
+      return Err(
+        "Error: --dump-wgsl is not supported when running a directory"
+          .to_string(),
+      );
+    }
+    if !def_overrides.is_empty() {
+      return Err(
+        "Error: --set is not supported when running a directory".to_string(),
+      );
+    }
+    playlist::run_playlist(&input, entry, interval, shuffle)
+  } else if watch {
     // AtomicBool polled by the IOManager's reload_requested() on every frame.
     let reload_flag = Arc::new(AtomicBool::new(false));
 
@@ -535,6 +854,30 @@ fn run_file(
       format!("Error: Failed to watch path {}\n{}", input.display(), e)
     })?;
 
+    // Terminal commands run on their own thread and trigger a reload the same
+    // way a file change does, which also restarts the program from its
+    // initial state.
+    {
+      let reload_flag = Arc::clone(&reload_flag);
+      let change_tx = change_tx.clone();
+      std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+          let Ok(line) = line else { break };
+          match line.trim() {
+            "" => {}
+            "r" => {
+              println!("Reloading...");
+              reload_flag.store(true, Ordering::Relaxed);
+              if change_tx.send(()).is_err() {
+                break;
+              }
+            }
+            other => eprintln!("Unknown command `{other}`, expected `r`"),
+          }
+        }
+      });
+    }
+
     {
       let reload_flag = Arc::clone(&reload_flag);
       let input = input.clone();
@@ -562,13 +905,24 @@ fn run_file(
     let mut last_content = read_source(&input)?;
 
     println!("Watching for changes... (Press Ctrl+C to stop)");
+    println!("Enter `r` to reload and restart the shader");
 
     loop {
-      // Compile current source.
-      let program = match try_get_validated_easl_program(&last_content) {
-        Ok(p) => p,
+      // Compile current source, with any `--set` overrides reapplied so
+      // they persist across reloads.
+      let program = match apply_def_overrides(&last_content, &def_overrides)
+        .and_then(|source| {
+          try_get_validated_easl_program(&source).map(|p| (p, source))
+        }) {
+        Ok((p, source)) => {
+          if let Some(path) = &dump_wgsl_path {
+            dump_wgsl(&source, path);
+          }
+          p
+        }
         Err(e) => {
           eprintln!("Compilation error:\n{e}");
+          println!("Waiting for a successful compile...");
           close_persistent_window();
           change_rx
             .recv()
@@ -603,7 +957,7 @@ fn run_file(
             // re-read; no need to block.
             while change_rx.try_recv().is_ok() {}
             last_content = read_source(&input)?;
-            println!("\n{} changed, reloading...", input.display());
+            println!("\nReloading {}...", input.display());
           } else {
             // Program finished on its own (e.g. user closed the window).
             // Exit the process rather than keeping the watcher alive.
@@ -614,8 +968,12 @@ fn run_file(
       }
     }
   } else {
-    let easl_source = read_source(&input)?;
+    let easl_source =
+      apply_def_overrides(&read_source(&input)?, &def_overrides)?;
     let program = try_get_validated_easl_program(&easl_source)?;
+    if let Some(path) = &dump_wgsl_path {
+      dump_wgsl(&easl_source, path);
+    }
     match run_program_entry(program, entry.as_ref().map(|s| s.as_str())) {
       Err(e) => return Err(format!("{e:?}")),
       _ => {}
@@ -633,18 +991,28 @@ fn main() {
     Command::Compile {
       input,
       output,
+      emit,
       watch,
-    } => compile_file(input, output, watch),
-    Command::Check { input } => check_file(input),
+      project,
+      timings_history,
+    } => compile_file(input, output, watch, project, emit, timings_history),
+    Command::Check { input, project } => check_file(input, project),
+    Command::Lint { input, json } => lint_file(input, json),
+    Command::Stats { input, json } => stats_file(input, json),
     Command::Format { input, output } => format_file(input, output),
+    Command::Watch { config } => watch_project(config),
     Command::Run {
       input,
       entry,
       watch,
+      interval,
+      shuffle,
+      dump_wgsl,
+      set,
     } => {
       #[cfg(feature = "interpreter")]
       {
-        run_file(input, entry, watch)
+        run_file(input, entry, watch, interval, shuffle, dump_wgsl, set)
       }
       #[cfg(not(feature = "interpreter"))]
       {
@@ -0,0 +1,136 @@
+#[cfg(feature = "interpreter")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "interpreter")]
+use crate::toplevel::{token_spans, top_level_forms};
+
+/// Parses a `NAME=VALUE` command line argument.
+pub fn parse_def_override(arg: &str) -> Result<(String, String), String> {
+  match arg.split_once('=') {
+    Some((name, value))
+      if !name.trim().is_empty() && !value.trim().is_empty() =>
+    {
+      Ok((name.trim().to_string(), value.trim().to_string()))
+    }
+    _ => Err(format!("expected NAME=VALUE, got `{arg}`")),
+  }
+}
+
+/// Replaces the values of top-level `(def ...)` forms in `source`. The new
+/// values are substituted as source text, so they are typechecked by the
+/// compiler like the originals. When a name is given more than once, the
+/// last value wins.
+#[cfg(feature = "interpreter")]
+pub fn apply_def_overrides(
+  source: &str,
+  overrides: &[(String, String)],
+) -> Result<String, String> {
+  if overrides.is_empty() {
+    return Ok(source.to_string());
+  }
+
+  // Each def can only be replaced once, since every span refers to the
+  // original source.
+  let overrides = overrides
+    .iter()
+    .map(|(name, value)| (name, value))
+    .collect::<BTreeMap<_, _>>();
+
+  let forms = top_level_forms(source);
+  let mut replacements = Vec::new();
+  for (name, value) in overrides {
+    let Some(form) = forms
+      .iter()
+      .find(|form| form.kind == "def" && &form.name == name)
+    else {
+      return Err(format!("Error: No top-level def named `{name}` to set"));
+    };
+    let inner_start = form.span.start + 1;
+    let Some(value_span) =
+      token_spans(&source[inner_start..form.span.end - 1]).pop()
+    else {
+      return Err(format!("Error: The def `{name}` has no value to replace"));
+    };
+    replacements.push((
+      inner_start + value_span.start..inner_start + value_span.end,
+      value,
+    ));
+  }
+
+  // Replace from the end of the source backwards so earlier spans stay
+  // valid.
+  replacements.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+  let mut source = source.to_string();
+  for (span, value) in replacements {
+    source.replace_range(span, value);
+  }
+  Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_name_value_pairs() {
+    assert_eq!(
+      parse_def_override(" iterations = 128 "),
+      Ok(("iterations".to_string(), "128".to_string()))
+    );
+    assert!(parse_def_override("iterations").is_err());
+    assert!(parse_def_override(" =3").is_err());
+    assert!(parse_def_override("iterations= ").is_err());
+  }
+
+  #[cfg(feature = "interpreter")]
+  fn set(source: &str, overrides: &[(&str, &str)]) -> String {
+    let overrides = overrides
+      .iter()
+      .map(|(name, value)| (name.to_string(), value.to_string()))
+      .collect::<Vec<_>>();
+    apply_def_overrides(source, &overrides).unwrap()
+  }
+
+  #[cfg(feature = "interpreter")]
+  #[test]
+  fn replaces_typed_def() {
+    assert_eq!(set("(def n: u32 5)\n", &[("n", "64")]), "(def n: u32 64)\n");
+  }
+
+  #[cfg(feature = "interpreter")]
+  #[test]
+  fn keeps_comments() {
+    assert_eq!(
+      set("(def a ; the value\n  10) ; ten\n", &[("a", "1")]),
+      "(def a ; the value\n  1) ; ten\n"
+    );
+  }
+
+  #[cfg(feature = "interpreter")]
+  #[test]
+  fn replaces_several_defs() {
+    assert_eq!(
+      set(
+        "(def a 10)\n(def b: f32 0.5)\n(def c 3)\n",
+        &[("c", "300"), ("a", "1")]
+      ),
+      "(def a 1)\n(def b: f32 0.5)\n(def c 300)\n"
+    );
+  }
+
+  #[cfg(feature = "interpreter")]
+  #[test]
+  fn last_duplicate_wins() {
+    assert_eq!(
+      set("(def a 10)\n(def b 2)\n", &[("a", "1"), ("a", "200")]),
+      "(def a 200)\n(def b 2)\n"
+    );
+  }
+
+  #[cfg(feature = "interpreter")]
+  #[test]
+  fn unknown_def_is_an_error() {
+    let overrides = [("missing".to_string(), "1".to_string())];
+    assert!(apply_def_overrides("(defn missing [] 1)", &overrides).is_err());
+  }
+}
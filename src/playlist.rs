@@ -0,0 +1,241 @@
+use std::hash::{BuildHasher, RandomState};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::time::Duration;
+
+use easl::interpreter::{
+  IOManager, StdoutIO, close_persistent_window, run_program_entry_with_io,
+};
+
+use crate::{find_easl_files, read_source, try_get_validated_easl_program};
+
+#[derive(Clone, Copy)]
+enum PlaylistStep {
+  Next,
+  Previous,
+  Select(usize),
+}
+
+fn shuffle<T>(items: &mut [T]) {
+  let state = RandomState::new();
+  for i in (1..items.len()).rev() {
+    let j = (state.hash_one(i) % (i as u64 + 1)) as usize;
+    items.swap(i, j);
+  }
+}
+
+/// The next shader from `index` in the given direction that hasn't been
+/// skipped, wrapping around the playlist and ending back at `index`.
+fn next_playable(
+  skipped: &[bool],
+  index: usize,
+  forward: bool,
+) -> Option<usize> {
+  let len = skipped.len();
+  (1..=len)
+    .map(|offset| {
+      if forward {
+        (index + offset) % len
+      } else {
+        (index + len - offset) % len
+      }
+    })
+    .find(|&i| !skipped[i])
+}
+
+fn pick_shader(shader_count: usize) -> Result<usize, String> {
+  loop {
+    print!("Choose a shader to start with [1-{shader_count}]: ");
+    std::io::stdout()
+      .flush()
+      .map_err(|e| format!("Error: Failed to flush stdout\n{e}"))?;
+    let mut line = String::new();
+    let bytes_read = std::io::stdin()
+      .read_line(&mut line)
+      .map_err(|e| format!("Error: Failed to read from stdin\n{e}"))?;
+    // Without a terminal to read from, or on an empty answer, start at the
+    // top of the list.
+    if bytes_read == 0 || line.trim().is_empty() {
+      return Ok(0);
+    }
+    match line.trim().parse::<usize>() {
+      Ok(number) if (1..=shader_count).contains(&number) => {
+        return Ok(number - 1);
+      }
+      _ => eprintln!("Expected a number between 1 and {shader_count}"),
+    }
+  }
+}
+
+fn spawn_step_reader(step_tx: Sender<PlaylistStep>, shader_count: usize) {
+  std::thread::spawn(move || {
+    for line in std::io::stdin().lock().lines() {
+      let Ok(line) = line else { break };
+      let step = match line.trim() {
+        "" => continue,
+        "n" => PlaylistStep::Next,
+        "p" => PlaylistStep::Previous,
+        other => match other.parse::<usize>() {
+          Ok(number) if (1..=shader_count).contains(&number) => {
+            PlaylistStep::Select(number - 1)
+          }
+          _ => {
+            eprintln!(
+              "Unknown command `{other}`, expected `n`, `p`, or a shader \
+               number"
+            );
+            continue;
+          }
+        },
+      };
+      if step_tx.send(step).is_err() {
+        break;
+      }
+    }
+  });
+}
+
+pub fn run_playlist(
+  dir: &PathBuf,
+  entry: Option<String>,
+  interval: Option<Duration>,
+  shuffle_order: bool,
+) -> Result<(), String> {
+  let mut files = find_easl_files(dir)?;
+  files.sort();
+  if shuffle_order {
+    shuffle(&mut files);
+  }
+
+  // Validate every shader up front, so a broken file is reported once at
+  // startup rather than interrupting playback when its turn comes.
+  let mut playlist = Vec::new();
+  for file in files {
+    let source = read_source(&file)?;
+    match try_get_validated_easl_program(&source) {
+      Ok(_) => playlist.push((file, source)),
+      Err(e) => eprintln!("Skipping {}:\n{e}\n", file.display()),
+    }
+  }
+
+  if playlist.is_empty() {
+    return Err(format!(
+      "No valid .easl files found in directory {}",
+      dir.display()
+    ));
+  }
+
+  println!(
+    "Playing {} shader(s) from {}",
+    playlist.len(),
+    dir.display()
+  );
+  for (i, (file, _)) in playlist.iter().enumerate() {
+    println!("  {}: {}", i + 1, file.display());
+  }
+
+  // With an interval the playlist runs unattended from the top, otherwise
+  // let the user pick where to start.
+  let mut index = if interval.is_some() {
+    0
+  } else {
+    pick_shader(playlist.len())?
+  };
+
+  println!(
+    "Enter `n` for the next shader, `p` for the previous one, or a number \
+     to switch to that shader"
+  );
+
+  // Steps from stdin and the interval timer are funneled through a single
+  // switcher thread, which forwards them to the main loop and raises the
+  // reload flag so the running program hands control back.
+  let reload_flag = Arc::new(AtomicBool::new(false));
+  let (step_tx, step_rx) = channel();
+  let (switch_tx, switch_rx) = channel();
+  spawn_step_reader(step_tx, playlist.len());
+  {
+    let reload_flag = Arc::clone(&reload_flag);
+    std::thread::spawn(move || {
+      loop {
+        let step = match interval {
+          Some(interval) => match step_rx.recv_timeout(interval) {
+            Ok(step) => step,
+            Err(RecvTimeoutError::Timeout) => PlaylistStep::Next,
+            Err(RecvTimeoutError::Disconnected) => {
+              // stdin closed, keep cycling on the timer alone.
+              std::thread::sleep(interval);
+              PlaylistStep::Next
+            }
+          },
+          None => match step_rx.recv() {
+            Ok(step) => step,
+            Err(_) => break,
+          },
+        };
+        if switch_tx.send(step).is_err() {
+          break;
+        }
+        reload_flag.store(true, Ordering::Relaxed);
+      }
+    });
+  }
+
+  let mut io = StdoutIO::with_reload_flag(Arc::clone(&reload_flag));
+  // Shaders that hit a runtime error are skipped rather than removed, so the
+  // numbers listed at startup keep referring to the same shaders.
+  let mut skipped = vec![false; playlist.len()];
+
+  loop {
+    let (path, source) = &playlist[index];
+    println!("\nPlaying {}", path.display());
+    let program = try_get_validated_easl_program(source)?;
+
+    match run_program_entry_with_io(program, entry.as_deref(), io) {
+      Err(e) => {
+        // Skip the failing shader and keep the show going with the rest.
+        eprintln!("Runtime error in {}: {e:?}", path.display());
+        io = StdoutIO::with_reload_flag(Arc::clone(&reload_flag));
+        skipped[index] = true;
+        let Some(next) = next_playable(&skipped, index, true) else {
+          close_persistent_window();
+          return Err("Every shader in the playlist failed".to_string());
+        };
+        index = next;
+      }
+      Ok((returned_io, did_reload)) => {
+        io = returned_io;
+        if !did_reload {
+          // The window was closed, stop the playlist.
+          close_persistent_window();
+          return Ok(());
+        }
+        // Clear the reload flag before taking the pending steps, so a step
+        // that arrives after this point raises it again for the next shader
+        // instead of being cleared unseen.
+        io.reset_for_reload();
+        while let Ok(step) = switch_rx.try_recv() {
+          index = match step {
+            PlaylistStep::Next => {
+              next_playable(&skipped, index, true).unwrap_or(index)
+            }
+            PlaylistStep::Previous => {
+              next_playable(&skipped, index, false).unwrap_or(index)
+            }
+            PlaylistStep::Select(selected) if skipped[selected] => {
+              eprintln!(
+                "Shader {} is skipped after a runtime error",
+                selected + 1
+              );
+              index
+            }
+            PlaylistStep::Select(selected) => selected,
+          };
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,432 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::read_source;
+use crate::toplevel::{normalized, symbols, tokens, top_level_forms};
+
+struct Declaration {
+  file: PathBuf,
+  line: usize,
+  kind: String,
+  is_entry: bool,
+  /// Parameter types of a function, which tell its overloads apart. Empty
+  /// for every other kind of form.
+  overload: String,
+  // Normalized text of the form and its metadata, for comparing
+  // declarations independent of formatting.
+  signature: String,
+}
+
+fn read_sources(files: &[PathBuf]) -> Result<Vec<(PathBuf, String)>, String> {
+  files
+    .iter()
+    .map(|file| Ok((file.clone(), read_source(file)?)))
+    .collect()
+}
+
+/// The types in a parameter list like `[x: f32 @builtin position p: vec4f]`.
+fn parameter_types(parameters: &str) -> String {
+  let Some(inner) = parameters
+    .strip_prefix('[')
+    .and_then(|rest| rest.strip_suffix(']'))
+  else {
+    return String::new();
+  };
+  let elements = tokens(inner);
+  elements
+    .iter()
+    .zip(elements.iter().skip(1))
+    .filter(|(element, _)| element.ends_with(':'))
+    .map(|(_, ty)| normalized(ty))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn describe_locations<'a>(
+  declarations: impl Iterator<Item = &'a Declaration>,
+) -> String {
+  declarations
+    .map(|declaration| {
+      format!(
+        "  {}:{} ({})",
+        declaration.file.display(),
+        declaration.line,
+        declaration.kind
+      )
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Checks that the top-level definitions of a set of files that make up one
+/// project are consistent with each other, returning a description of every
+/// conflict found.
+pub fn check_project_consistency(files: &[PathBuf]) -> Result<(), String> {
+  check_sources_consistency(&read_sources(files)?)
+}
+
+fn check_sources_consistency(
+  sources: &[(PathBuf, String)],
+) -> Result<(), String> {
+  let mut declarations: BTreeMap<String, Vec<Declaration>> = BTreeMap::new();
+  for (file, source) in sources {
+    for form in top_level_forms(source) {
+      // Metadata pairs are sorted by key, since their order doesn't matter.
+      let metadata = form
+        .metadata
+        .iter()
+        .map(|(key, value)| (key, value))
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| format!("{key} {value}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+      let text = &source[form.span.clone()];
+      let overload = if form.kind == "defn" {
+        tokens(&text[1..text.len() - 1])
+          .get(2)
+          .map_or_else(String::new, |parameters| parameter_types(parameters))
+      } else {
+        String::new()
+      };
+      declarations
+        .entry(form.name.clone())
+        .or_default()
+        .push(Declaration {
+          file: file.clone(),
+          line: form.line,
+          kind: form.kind.clone(),
+          is_entry: form.stage().is_some(),
+          overload,
+          signature: format!("{metadata} {}", normalized(text)),
+        });
+    }
+  }
+
+  let mut diagnostics = Vec::new();
+  for (name, declarations) in &declarations {
+    let files = declarations
+      .iter()
+      .map(|declaration| &declaration.file)
+      .collect::<BTreeSet<_>>();
+    if files.len() < 2 {
+      continue;
+    }
+
+    let files_with_entry = declarations
+      .iter()
+      .filter(|declaration| declaration.is_entry)
+      .map(|declaration| &declaration.file)
+      .collect::<BTreeSet<_>>();
+    if files_with_entry.len() > 1 {
+      diagnostics.push(format!(
+        "Entry point `{name}` is declared in multiple files:\n{}",
+        describe_locations(
+          declarations
+            .iter()
+            .filter(|declaration| declaration.is_entry)
+        )
+      ));
+      continue;
+    }
+
+    let kinds = declarations
+      .iter()
+      .map(|declaration| &declaration.kind)
+      .collect::<BTreeSet<_>>();
+    if kinds.len() > 1 {
+      diagnostics.push(format!(
+        "Conflicting definitions of `{name}`:\n{}",
+        describe_locations(declarations.iter())
+      ));
+      continue;
+    }
+
+    // Overloads of a function may be split across files, so only
+    // declarations of the same overload have to agree.
+    let mut overloads: BTreeMap<&str, Vec<&Declaration>> = BTreeMap::new();
+    for declaration in declarations {
+      overloads
+        .entry(&declaration.overload)
+        .or_default()
+        .push(declaration);
+    }
+    for overload in overloads.values() {
+      let signatures = overload
+        .iter()
+        .map(|declaration| &declaration.signature)
+        .collect::<BTreeSet<_>>();
+      if signatures.len() < 2 {
+        continue;
+      }
+      let description =
+        if overload.iter().all(|declaration| declaration.kind == "var") {
+          "Mismatched declarations"
+        } else {
+          "Conflicting definitions"
+        };
+      diagnostics.push(format!(
+        "{description} of `{name}`:\n{}",
+        describe_locations(overload.iter().copied())
+      ));
+    }
+  }
+
+  if diagnostics.is_empty() {
+    Ok(())
+  } else {
+    Err(format!(
+      "Project consistency check failed:\n\n{}",
+      diagnostics.join("\n\n")
+    ))
+  }
+}
+
+#[derive(Serialize)]
+pub struct UnusedDefinition {
+  pub file: PathBuf,
+  pub line: usize,
+  pub kind: String,
+  pub name: String,
+}
+
+/// Finds the functions, defs, and vars in a set of files that can't be
+/// reached from any entry point. Names are resolved across all of the files,
+/// so a helper used by an entry in another file still counts as used.
+pub fn find_unused_definitions(
+  files: &[PathBuf],
+) -> Result<Vec<UnusedDefinition>, String> {
+  Ok(unused_definitions_in_sources(&read_sources(files)?))
+}
+
+fn unused_definitions_in_sources(
+  sources: &[(PathBuf, String)],
+) -> Vec<UnusedDefinition> {
+  struct Definition {
+    file: PathBuf,
+    line: usize,
+    kind: String,
+    name: String,
+    is_entry: bool,
+    references: Vec<String>,
+  }
+
+  let mut definitions = Vec::new();
+  for (file, source) in sources {
+    for form in top_level_forms(source) {
+      definitions.push(Definition {
+        file: file.clone(),
+        line: form.line,
+        is_entry: form.stage().is_some(),
+        // Field access like `u.time` refers to `u`.
+        references: symbols(&source[form.span.clone()])
+          .into_iter()
+          .map(|symbol| {
+            symbol.split('.').next().unwrap_or_default().to_string()
+          })
+          .collect(),
+        kind: form.kind,
+        name: form.name,
+      });
+    }
+  }
+
+  let mut definitions_by_name: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+  for (i, definition) in definitions.iter().enumerate() {
+    definitions_by_name
+      .entry(&definition.name)
+      .or_default()
+      .push(i);
+  }
+
+  let mut reachable = vec![false; definitions.len()];
+  let mut pending: Vec<usize> = definitions
+    .iter()
+    .enumerate()
+    .filter(|(_, definition)| definition.is_entry)
+    .map(|(i, _)| i)
+    .collect();
+  while let Some(i) = pending.pop() {
+    if reachable[i] {
+      continue;
+    }
+    reachable[i] = true;
+    for reference in &definitions[i].references {
+      // Every overload of a referenced name is treated as used.
+      if let Some(referenced) = definitions_by_name.get(reference.as_str()) {
+        pending.extend(referenced.iter().filter(|&&j| !reachable[j]));
+      }
+    }
+  }
+
+  definitions
+    .into_iter()
+    .zip(reachable)
+    .filter(|(definition, reachable)| {
+      !reachable && matches!(definition.kind.as_str(), "defn" | "def" | "var")
+    })
+    .map(|(definition, _)| UnusedDefinition {
+      file: definition.file,
+      line: definition.line,
+      kind: definition.kind,
+      name: definition.name,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sources(files: &[(&str, &str)]) -> Vec<(PathBuf, String)> {
+    files
+      .iter()
+      .map(|(file, source)| (PathBuf::from(file), source.to_string()))
+      .collect()
+  }
+
+  fn consistency_error(files: &[(&str, &str)]) -> String {
+    check_sources_consistency(&sources(files))
+      .expect_err("expected a consistency error")
+  }
+
+  #[test]
+  fn identical_declarations_are_consistent() {
+    let shared = "@{group 0 binding 0 address uniform}\n(var time: f32)\n\
+                  (defn double [x: f32]: f32 (* x 2.))";
+    assert!(
+      check_sources_consistency(&sources(&[
+        ("a.easl", shared),
+        ("b.easl", shared)
+      ]))
+      .is_ok()
+    );
+  }
+
+  #[test]
+  fn var_metadata_order_is_ignored() {
+    assert!(
+      check_sources_consistency(&sources(&[
+        (
+          "a.easl",
+          "@{group 0 binding 0 address uniform}\n(var u: f32)"
+        ),
+        (
+          "b.easl",
+          "@{address uniform\n  group 0 binding 0}\n(var u:  f32)"
+        ),
+      ]))
+      .is_ok()
+    );
+  }
+
+  #[test]
+  fn mismatched_var_bindings() {
+    let error = consistency_error(&[
+      ("a.easl", "@{group 0 binding 0}\n(var u: f32)"),
+      ("b.easl", "@{group 0 binding 1}\n(var u: f32)"),
+    ]);
+    assert!(error.contains("Mismatched declarations of `u`"), "{error}");
+  }
+
+  #[test]
+  fn overloads_split_across_files() {
+    assert!(
+      check_sources_consistency(&sources(&[
+        (
+          "a.easl",
+          "(defn f [x: f32]: f32 x)\n(defn f [x: vec2f]: vec2f x)"
+        ),
+        ("b.easl", "(defn f [x: f32]: f32 x)"),
+        ("c.easl", "(defn f [x: vec3f]: vec3f x)"),
+      ]))
+      .is_ok()
+    );
+  }
+
+  #[test]
+  fn conflicting_overload() {
+    let error = consistency_error(&[
+      (
+        "a.easl",
+        "(defn f [x: f32]: f32 x)\n(defn f [x: vec2f]: vec2f x)",
+      ),
+      ("b.easl", "(defn f [y: f32]: f32 (* y 2.))"),
+    ]);
+    assert!(error.contains("Conflicting definitions of `f`"), "{error}");
+    assert!(error.contains("a.easl:1"), "{error}");
+    assert!(!error.contains("a.easl:2"), "{error}");
+  }
+
+  #[test]
+  fn conflicting_kinds() {
+    let error = consistency_error(&[
+      ("a.easl", "(def f 1.)"),
+      ("b.easl", "(defn f []: f32 1.)"),
+    ]);
+    assert!(error.contains("Conflicting definitions of `f`"), "{error}");
+  }
+
+  #[test]
+  fn entry_point_in_multiple_files() {
+    let entry = "@fragment\n(defn frag []: vec4f (vec4f 1.))";
+    let error = consistency_error(&[("a.easl", entry), ("b.easl", entry)]);
+    assert!(
+      error.contains("Entry point `frag` is declared in multiple files"),
+      "{error}"
+    );
+  }
+
+  fn unused_names(files: &[(&str, &str)]) -> Vec<String> {
+    unused_definitions_in_sources(&sources(files))
+      .into_iter()
+      .map(|definition| format!("{}:{}", definition.kind, definition.name))
+      .collect()
+  }
+
+  #[test]
+  fn field_access_uses_the_var() {
+    assert!(
+      unused_names(&[(
+        "a.easl",
+        "@{group 0 binding 0 address uniform}\n(var u: Uniforms)\n\
+         @fragment\n(defn frag []: vec4f (vec4f u.time))",
+      )])
+      .is_empty()
+    );
+  }
+
+  #[test]
+  fn helpers_are_resolved_across_files() {
+    assert_eq!(
+      unused_names(&[
+        (
+          "a.easl",
+          "@compute\n(defn main [] (helper 1.))\n(defn dead [] 0)"
+        ),
+        (
+          "b.easl",
+          "(def scale 2.)\n(defn helper [x: f32]: f32 (* x scale))"
+        ),
+      ]),
+      ["defn:dead"]
+    );
+  }
+
+  #[test]
+  fn unused_defs_and_vars_are_reported_with_lines() {
+    let unused = unused_definitions_in_sources(&sources(&[(
+      "a.easl",
+      "(struct Unused a: f32)\n(def unused-def 1)\n\
+       @{group 0 binding 0}\n(var unused-var: f32)\n\
+       @vertex\n(defn vert []: vec4f (vec4f 0.))",
+    )]));
+    let found = unused
+      .iter()
+      .map(|definition| (definition.name.as_str(), definition.line))
+      .collect::<Vec<_>>();
+    assert_eq!(found, [("unused-def", 2), ("unused-var", 4)]);
+  }
+}
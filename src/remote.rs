@@ -0,0 +1,125 @@
+//! Support for passing an http(s) URL in place of an input path. Every run
+//! fetches the source again, and the last successful fetch is kept in a
+//! per-user cache as a fallback, so a shader that has been run before still
+//! works when the network doesn't.
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+pub fn is_url(input: &Path) -> bool {
+  input
+    .to_str()
+    .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://"))
+}
+
+/// The file name at the end of a URL's path, e.g. `shader.easl`.
+pub fn url_file_name(url: &str) -> String {
+  url
+    .split(['?', '#'])
+    .next()
+    .and_then(|path| path.rsplit('/').next())
+    .filter(|name| !name.is_empty())
+    .unwrap_or("remote.easl")
+    .to_string()
+}
+
+/// The current user's cache directory for easl. It isn't in the shared temp
+/// directory, where another user could plant a source to be run in place of
+/// a failed fetch.
+fn cache_dir() -> Result<PathBuf, String> {
+  let non_empty = |var: &str| {
+    std::env::var_os(var)
+      .filter(|value| !value.is_empty())
+      .map(PathBuf::from)
+  };
+  non_empty("XDG_CACHE_HOME")
+    .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+    .or_else(|| non_empty("LOCALAPPDATA"))
+    .map(|dir| dir.join("easl"))
+    .ok_or_else(|| {
+      "Error: Couldn't find a cache directory for fetched sources, set \
+       XDG_CACHE_HOME"
+        .to_string()
+    })
+}
+
+fn cache_path(url: &str) -> Result<PathBuf, String> {
+  let hash = Sha256::digest(url.as_bytes())
+    .iter()
+    .take(8)
+    .map(|byte| format!("{byte:02x}"))
+    .collect::<String>();
+  Ok(
+    cache_dir()?
+      .join("remote")
+      .join(format!("{hash}-{}", url_file_name(url))),
+  )
+}
+
+/// Writes `source` to `path` through a freshly created temporary file that is
+/// renamed into place, so an existing symlink at either path is replaced
+/// rather than followed.
+fn write_cache(path: &Path, source: &str) -> Result<(), String> {
+  let write_error = |path: &Path, e: std::io::Error| {
+    format!(
+      "Error: Failed to write output file {}\n{}",
+      path.display(),
+      e
+    )
+  };
+  let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+  temp_name.push(".download");
+  let temp_path = path.with_file_name(temp_name);
+  if let Err(e) = fs::remove_file(&temp_path)
+    && e.kind() != ErrorKind::NotFound
+  {
+    return Err(write_error(&temp_path, e));
+  }
+  fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .open(&temp_path)
+    .and_then(|mut file| file.write_all(source.as_bytes()))
+    .map_err(|e| write_error(&temp_path, e))?;
+  fs::rename(&temp_path, path).map_err(|e| write_error(path, e))
+}
+
+fn download(url: &str) -> Result<String, String> {
+  ureq::get(url)
+    .call()
+    .map_err(|e| e.to_string())?
+    .body_mut()
+    .read_to_string()
+    .map_err(|e| e.to_string())
+}
+
+/// Downloads the source at `url` into the cache and returns the cached
+/// path. If the download fails, a previously cached copy is used instead.
+pub fn fetch_remote_source(url: &Path) -> Result<PathBuf, String> {
+  let url = url.to_string_lossy();
+  let path = cache_path(&url)?;
+  println!("Fetching {url}...");
+  match download(&url) {
+    Ok(source) => {
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+          format!(
+            "Error: Failed to create directory {}\n{}",
+            parent.display(),
+            e
+          )
+        })?;
+      }
+      write_cache(&path, &source)?;
+      Ok(path)
+    }
+    Err(e) if path.exists() => {
+      eprintln!("Failed to fetch {url}, using cached copy\n{e}");
+      Ok(path)
+    }
+    Err(e) => Err(format!("Error: Failed to fetch {url}\n{e}")),
+  }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::toplevel::top_level_forms;
+use crate::{read_source, try_compile_easl};
+
+const STAGES: [&str; 4] = ["vertex", "fragment", "compute", "cpu"];
+
+#[derive(Serialize, Default)]
+struct Stats {
+  lines: usize,
+  functions: usize,
+  entry_points: BTreeMap<String, usize>,
+  uniforms: usize,
+  /// Size of the generated WGSL, or `None` if the file failed to compile.
+  wgsl_bytes: Option<usize>,
+}
+
+impl Stats {
+  fn add(&mut self, other: &Stats) {
+    self.lines += other.lines;
+    self.functions += other.functions;
+    for (stage, count) in &other.entry_points {
+      *self.entry_points.entry(stage.clone()).or_default() += count;
+    }
+    self.uniforms += other.uniforms;
+    if let Some(bytes) = other.wgsl_bytes {
+      *self.wgsl_bytes.get_or_insert(0) += bytes;
+    }
+  }
+
+  fn row(&self, label: &str, label_width: usize) -> String {
+    let entries = STAGES
+      .iter()
+      .map(|stage| {
+        format!(
+          " {:>8}",
+          self.entry_points.get(*stage).copied().unwrap_or(0)
+        )
+      })
+      .collect::<String>();
+    format!(
+      "{label:<label_width$} {:>7} {:>9}{entries} {:>8} {:>10}",
+      self.lines,
+      self.functions,
+      self.uniforms,
+      self
+        .wgsl_bytes
+        .map_or_else(|| "failed".to_string(), |bytes| bytes.to_string())
+    )
+  }
+}
+
+#[derive(Serialize)]
+struct FileStats {
+  file: PathBuf,
+  #[serde(flatten)]
+  stats: Stats,
+}
+
+#[derive(Serialize)]
+struct StatsReport {
+  files: Vec<FileStats>,
+  total: Stats,
+}
+
+fn file_stats(file: &PathBuf) -> Result<Stats, String> {
+  let source = read_source(file)?;
+  let forms = top_level_forms(&source);
+  let mut entry_points = BTreeMap::new();
+  for stage in forms.iter().filter_map(|form| form.stage()) {
+    *entry_points.entry(stage.to_string()).or_default() += 1;
+  }
+  Ok(Stats {
+    lines: source.lines().count(),
+    functions: forms.iter().filter(|form| form.kind == "defn").count(),
+    entry_points,
+    uniforms: forms
+      .iter()
+      .filter(|form| {
+        form.kind == "var"
+          && form
+            .metadata
+            .iter()
+            .any(|(key, value)| key == "address" && value == "uniform")
+      })
+      .count(),
+    wgsl_bytes: try_compile_easl(&source).ok().map(|wgsl| wgsl.len()),
+  })
+}
+
+pub fn print_stats(files: &[PathBuf], json: bool) -> Result<(), String> {
+  let mut report = StatsReport {
+    files: Vec::new(),
+    total: Stats::default(),
+  };
+  for file in files {
+    let stats = file_stats(file)?;
+    report.total.add(&stats);
+    report.files.push(FileStats {
+      file: file.clone(),
+      stats,
+    });
+  }
+
+  if json {
+    println!(
+      "{}",
+      serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Error: Failed to serialize stats\n{e}"))?
+    );
+    return Ok(());
+  }
+
+  let labels = report
+    .files
+    .iter()
+    .map(|file_stats| file_stats.file.display().to_string())
+    .collect::<Vec<_>>();
+  let label_width = labels
+    .iter()
+    .map(String::len)
+    .chain(["total".len()])
+    .max()
+    .unwrap_or_default();
+  println!(
+    "{:<label_width$} {:>7} {:>9}{} {:>8} {:>10}",
+    "file",
+    "lines",
+    "functions",
+    STAGES
+      .iter()
+      .map(|stage| format!(" {stage:>8}"))
+      .collect::<String>(),
+    "uniforms",
+    "wgsl bytes"
+  );
+  for (label, file_stats) in labels.iter().zip(&report.files) {
+    println!("{}", file_stats.stats.row(label, label_width));
+  }
+  if report.files.len() > 1 {
+    println!("{}", report.total.row("total", label_width));
+  }
+  Ok(())
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+// A file is reported as regressed when it takes this many times longer than
+// its historical median, and the slowdown is also large enough in absolute
+// terms not to be noise.
+const REGRESSION_RATIO: f64 = 1.5;
+const REGRESSION_MIN_MS: f64 = 10.;
+const MIN_SAMPLES: usize = 3;
+
+#[derive(Serialize, Deserialize)]
+struct BuildTimings {
+  /// Seconds since the unix epoch when the build finished.
+  timestamp: u64,
+  /// Compile time in milliseconds for each file in the build.
+  files: BTreeMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct TimingsHistory {
+  builds: Vec<BuildTimings>,
+}
+
+fn median(mut samples: Vec<f64>) -> f64 {
+  samples.sort_by(f64::total_cmp);
+  let mid = samples.len() / 2;
+  if samples.len().is_multiple_of(2) {
+    (samples[mid - 1] + samples[mid]) / 2.
+  } else {
+    samples[mid]
+  }
+}
+
+/// Appends the compile times of a build to the history file, warning about
+/// any file that is much slower than it has been historically.
+pub fn record_build_timings(
+  history_path: &Path,
+  timings: &[(PathBuf, Duration)],
+) -> Result<(), String> {
+  let mut history = if history_path.exists() {
+    let content = fs::read_to_string(history_path).map_err(|e| {
+      format!(
+        "Error: Failed to read timings history {}\n{}",
+        history_path.display(),
+        e
+      )
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+      format!(
+        "Error: Failed to parse timings history {}\n{}",
+        history_path.display(),
+        e
+      )
+    })?
+  } else {
+    TimingsHistory::default()
+  };
+
+  // Files are keyed by their canonical path, since the same file can be
+  // given relative to the input or, by the file watcher, as an absolute path.
+  let files: BTreeMap<String, f64> = timings
+    .iter()
+    .map(|(file, time)| {
+      let file = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+      (file.display().to_string(), time.as_secs_f64() * 1000.)
+    })
+    .collect();
+
+  for (file, &ms) in &files {
+    let samples: Vec<f64> = history
+      .builds
+      .iter()
+      .filter_map(|build| build.files.get(file).copied())
+      .collect();
+    if samples.len() < MIN_SAMPLES {
+      continue;
+    }
+    let median_ms = median(samples);
+    if ms > median_ms * REGRESSION_RATIO && ms - median_ms > REGRESSION_MIN_MS {
+      eprintln!(
+        "Warning: {file} took {ms:.1}ms to compile, compared to a \
+         historical median of {median_ms:.1}ms"
+      );
+    }
+  }
+
+  history.builds.push(BuildTimings {
+    timestamp: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |elapsed| elapsed.as_secs()),
+    files,
+  });
+
+  let content = serde_json::to_string_pretty(&history)
+    .map_err(|e| format!("Error: Failed to serialize timings history\n{e}"))?;
+  fs::write(history_path, content).map_err(|e| {
+    format!(
+      "Error: Failed to write timings history {}\n{}",
+      history_path.display(),
+      e
+    )
+  })
+}
@@ -0,0 +1,337 @@
+//! Lightweight scanning of the top-level forms in a .easl file, for
+//! project-wide analysis that doesn't need a full compile. Malformed source
+//! is scanned as far as it is balanced, since the compiler is what reports
+//! syntax errors.
+
+use std::ops::Range;
+
+/// A top-level form such as `(defn ...)` or `(var ...)`, along with the
+/// annotations that precede it.
+pub struct TopLevelForm {
+  /// Head of the form, e.g. `defn`, `def`, `var`, or `struct`.
+  pub kind: String,
+  /// Name being defined by the form.
+  pub name: String,
+  /// Type written after the name, as in `(var time: f32)`.
+  pub declared_type: Option<String>,
+  /// Bare annotations before the form, e.g. `vertex` for `@vertex`.
+  pub annotations: Vec<String>,
+  /// Key/value pairs from `@{...}` metadata before the form.
+  pub metadata: Vec<(String, String)>,
+  /// Byte range of the form in the source, not including annotations.
+  pub span: Range<usize>,
+  /// 1-based line the form starts on.
+  pub line: usize,
+}
+
+impl TopLevelForm {
+  /// The shader stage of an entry point, if the form is one.
+  pub fn stage(&self) -> Option<&str> {
+    self
+      .annotations
+      .iter()
+      .map(String::as_str)
+      .find(|annotation| {
+        matches!(*annotation, "vertex" | "fragment" | "compute" | "cpu")
+      })
+  }
+}
+
+fn is_delimiter(byte: u8) -> bool {
+  byte.is_ascii_whitespace()
+    || matches!(byte, b'(' | b')' | b'[' | b']' | b'{' | b'}' | b';' | b'"')
+}
+
+fn skip_comment(bytes: &[u8], start: usize) -> usize {
+  bytes[start..]
+    .iter()
+    .position(|&b| b == b'\n')
+    .map_or(bytes.len(), |offset| start + offset)
+}
+
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+  let mut i = start + 1;
+  while i < bytes.len() {
+    match bytes[i] {
+      b'\\' => i += 2,
+      b'"' => return i + 1,
+      _ => i += 1,
+    }
+  }
+  bytes.len()
+}
+
+fn symbol_end(bytes: &[u8], start: usize) -> usize {
+  bytes[start..]
+    .iter()
+    .position(|&b| is_delimiter(b))
+    .map_or(bytes.len(), |offset| start + offset)
+}
+
+/// Given the index of an opening bracket, returns the index just past its
+/// matching close, or `None` if the source ends first.
+fn form_end(bytes: &[u8], start: usize) -> Option<usize> {
+  let mut depth = 0;
+  let mut i = start;
+  while i < bytes.len() {
+    match bytes[i] {
+      b';' => i = skip_comment(bytes, i),
+      b'"' => i = skip_string(bytes, i),
+      b'(' | b'[' | b'{' => {
+        depth += 1;
+        i += 1;
+      }
+      b')' | b']' | b'}' => {
+        depth -= 1;
+        i += 1;
+        if depth == 0 {
+          return Some(i);
+        }
+      }
+      _ => i += 1,
+    }
+  }
+  None
+}
+
+/// Byte ranges of the elements inside a list, keeping nested lists whole and
+/// dropping comments.
+pub fn token_spans(text: &str) -> Vec<Range<usize>> {
+  let bytes = text.as_bytes();
+  let mut spans = Vec::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    let end = match bytes[i] {
+      b if b.is_ascii_whitespace() || matches!(b, b')' | b']' | b'}') => {
+        i += 1;
+        continue;
+      }
+      b';' => {
+        i = skip_comment(bytes, i);
+        continue;
+      }
+      b'"' => skip_string(bytes, i),
+      b'(' | b'[' | b'{' => form_end(bytes, i).unwrap_or(bytes.len()),
+      _ => symbol_end(bytes, i),
+    };
+    spans.push(i..end);
+    i = end;
+  }
+  spans
+}
+
+/// Splits the inside of a list into its elements, as with [`token_spans`].
+pub fn tokens(text: &str) -> Vec<&str> {
+  token_spans(text)
+    .into_iter()
+    .map(|span| &text[span])
+    .collect()
+}
+
+/// Every symbol in `text` at any nesting depth, with comments dropped and
+/// the colon of a `name:` type annotation trimmed.
+pub fn symbols(text: &str) -> Vec<&str> {
+  let bytes = text.as_bytes();
+  let mut symbols = Vec::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    match bytes[i] {
+      b';' => i = skip_comment(bytes, i),
+      b'"' => i = skip_string(bytes, i),
+      b if is_delimiter(b) => i += 1,
+      _ => {
+        let end = symbol_end(bytes, i);
+        symbols.push(text[i..end].trim_end_matches(':'));
+        i = end;
+      }
+    }
+  }
+  symbols
+}
+
+/// Collapses whitespace and drops comments, so that two forms can be
+/// compared independent of formatting.
+pub fn normalized(text: &str) -> String {
+  text
+    .lines()
+    .map(|line| line.split(';').next().unwrap_or_default())
+    .flat_map(str::split_whitespace)
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn declared_type(elements: &[&str]) -> Option<String> {
+  match elements {
+    [_, name, ty, ..] if name.ends_with(':') => Some(ty.to_string()),
+    [_, _, ":", ty, ..] => Some(ty.to_string()),
+    _ => None,
+  }
+}
+
+fn form_name(token: &str) -> String {
+  let name = if token.starts_with(['(', '[']) {
+    // Generic definitions like `(struct (Foo T) ...)`.
+    tokens(&token[1..]).first().copied().unwrap_or_default()
+  } else {
+    token
+  };
+  name.trim_end_matches(':').to_string()
+}
+
+pub fn top_level_forms(source: &str) -> Vec<TopLevelForm> {
+  let bytes = source.as_bytes();
+  let mut forms = Vec::new();
+  let mut annotations = Vec::new();
+  let mut metadata = Vec::new();
+  let mut line = 1;
+  let mut i = 0;
+  while i < bytes.len() {
+    match bytes[i] {
+      b'\n' => {
+        line += 1;
+        i += 1;
+      }
+      b';' => i = skip_comment(bytes, i),
+      b'@' if bytes.get(i + 1) == Some(&b'{') => {
+        let Some(end) = form_end(bytes, i + 1) else {
+          break;
+        };
+        let pairs = tokens(&source[i + 2..end - 1]);
+        metadata.extend(pairs.chunks(2).map(|pair| {
+          (
+            pair[0].to_string(),
+            pair.get(1).map_or_else(String::new, |v| v.to_string()),
+          )
+        }));
+        line += source[i..end].matches('\n').count();
+        i = end;
+      }
+      b'@' => {
+        let end = symbol_end(bytes, i + 1);
+        annotations.push(source[i + 1..end].to_string());
+        i = end;
+      }
+      b'(' => {
+        let Some(end) = form_end(bytes, i) else {
+          break;
+        };
+        let elements = tokens(&source[i + 1..end - 1]);
+        forms.push(TopLevelForm {
+          kind: elements.first().copied().unwrap_or_default().to_string(),
+          name: elements.get(1).map_or_else(String::new, |t| form_name(t)),
+          declared_type: declared_type(&elements),
+          annotations: std::mem::take(&mut annotations),
+          metadata: std::mem::take(&mut metadata),
+          span: i..end,
+          line,
+        });
+        line += source[i..end].matches('\n').count();
+        i = end;
+      }
+      _ => i += 1,
+    }
+  }
+  forms
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn forms_with_multi_line_metadata() {
+    let source = "\
+@{group 0
+  binding 1
+  address uniform}
+(var resolution: vec2f)
+
+(var time: f32)
+";
+    let forms = top_level_forms(source);
+    assert_eq!(forms.len(), 2);
+    assert_eq!(forms[0].kind, "var");
+    assert_eq!(forms[0].name, "resolution");
+    assert_eq!(forms[0].declared_type.as_deref(), Some("vec2f"));
+    assert_eq!(
+      forms[0].metadata,
+      vec![
+        ("group".to_string(), "0".to_string()),
+        ("binding".to_string(), "1".to_string()),
+        ("address".to_string(), "uniform".to_string()),
+      ]
+    );
+    assert_eq!(forms[0].line, 4);
+    assert_eq!(&source[forms[0].span.clone()], "(var resolution: vec2f)");
+    assert_eq!(forms[1].name, "time");
+    assert!(forms[1].metadata.is_empty());
+    assert_eq!(forms[1].line, 6);
+  }
+
+  #[test]
+  fn forms_with_annotations_and_overloads() {
+    let source = "\
+(defn double [x: f32]: f32 (* x 2.))
+(defn double [x: i32]: i32 (* x 2))
+@vertex
+(defn vert [@builtin vertex-index index: u32]: vec4f (vec4f 0.))
+";
+    let forms = top_level_forms(source);
+    let names = forms.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["double", "double", "vert"]);
+    assert_eq!(forms[0].stage(), None);
+    assert_eq!(forms[2].annotations, ["vertex"]);
+    assert_eq!(forms[2].stage(), Some("vertex"));
+    assert_eq!(forms[2].line, 4);
+  }
+
+  #[test]
+  fn generic_struct_name() {
+    let forms = top_level_forms("(struct (Pair T) first: T second: T)");
+    assert_eq!(forms[0].kind, "struct");
+    assert_eq!(forms[0].name, "Pair");
+  }
+
+  #[test]
+  fn comments_and_strings_are_not_forms() {
+    let source = "\
+; (defn commented-out [] 0)
+(def label \"(not a form)\") ; (def trailing 1)
+(def ten 10)
+";
+    let forms = top_level_forms(source);
+    let names = forms.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["label", "ten"]);
+    assert_eq!(forms[1].line, 3);
+  }
+
+  #[test]
+  fn unbalanced_source_stops_scanning() {
+    let forms = top_level_forms("(def a 1)\n(defn b [] (+ 1 2)");
+    assert_eq!(forms.len(), 1);
+    assert_eq!(forms[0].name, "a");
+  }
+
+  #[test]
+  fn token_spans_keep_nested_lists_whole() {
+    let text = "defn f [x: f32] ; comment\n (+ x \"a b\")";
+    assert_eq!(tokens(text), ["defn", "f", "[x: f32]", "(+ x \"a b\")"]);
+    assert_eq!(token_spans(text)[2], 7..15);
+  }
+
+  #[test]
+  fn symbols_at_any_depth() {
+    assert_eq!(
+      symbols("(vec4f u.time [x: f32] \"str sym\") ; comment sym"),
+      ["vec4f", "u.time", "x", "f32"]
+    );
+  }
+
+  #[test]
+  fn normalized_ignores_formatting_and_comments() {
+    assert_eq!(
+      normalized("(var  time:\n    f32) ; seconds"),
+      normalized("(var time: f32)")
+    );
+  }
+}
@@ -0,0 +1,231 @@
+//! `easl watch`, which reruns a sequence of steps over a project whenever a
+//! file in it changes, as configured by an `easl.toml` file:
+//!
+//! ```toml
+//! # Directory containing the project's .easl files, relative to easl.toml
+//! source = "shaders"
+//! # Steps to run on each change, in order
+//! steps = ["format", "check", "compile"]
+//! # Output directory for the compile step
+//! output = "build"
+//! # Shell command to run after every step has succeeded
+//! exec = "cp build/*.wgsl ../app/assets/shaders/"
+//! ```
+//!
+//! The `exec` command runs to completion before changes are watched for
+//! again, so it should be one that exits rather than a long-running app.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::channel;
+
+use notify::{
+  Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use serde::Deserialize;
+
+use crate::{Emit, check_file, compile_once, find_easl_files, format_file};
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum WatchStep {
+  Format,
+  Check,
+  Compile,
+}
+
+impl WatchStep {
+  fn name(self) -> &'static str {
+    match self {
+      WatchStep::Format => "format",
+      WatchStep::Check => "check",
+      WatchStep::Compile => "compile",
+    }
+  }
+}
+
+fn default_source() -> PathBuf {
+  PathBuf::from(".")
+}
+
+fn default_steps() -> Vec<WatchStep> {
+  vec![WatchStep::Format, WatchStep::Check, WatchStep::Compile]
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchConfig {
+  #[serde(default = "default_source")]
+  source: PathBuf,
+  #[serde(default = "default_steps")]
+  steps: Vec<WatchStep>,
+  output: Option<PathBuf>,
+  exec: Option<String>,
+}
+
+/// Directory containing the config file. A bare file name like `easl.toml`
+/// has an empty parent, which is the current directory.
+fn config_dir(config_path: &Path) -> &Path {
+  match config_path.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent,
+    _ => Path::new("."),
+  }
+}
+
+fn load_config(config_path: &Path) -> Result<WatchConfig, String> {
+  let content = fs::read_to_string(config_path).map_err(|e| {
+    format!(
+      "Error: Failed to read config file {}\n{}",
+      config_path.display(),
+      e
+    )
+  })?;
+  let mut config: WatchConfig = toml::from_str(&content).map_err(|e| {
+    format!(
+      "Error: Failed to parse config file {}\n{}",
+      config_path.display(),
+      e
+    )
+  })?;
+
+  // Paths in the config are relative to the config file.
+  let base = config_dir(config_path);
+  config.source = base.join(&config.source);
+  config.output = config.output.map(|output| base.join(output));
+  Ok(config)
+}
+
+fn run_exec_hook(command: &str, dir: &Path) -> Result<(), String> {
+  let mut shell = if cfg!(windows) {
+    let mut shell = process::Command::new("cmd");
+    shell.arg("/C");
+    shell
+  } else {
+    let mut shell = process::Command::new("sh");
+    shell.arg("-c");
+    shell
+  };
+  let status = shell
+    .arg(command)
+    .current_dir(dir)
+    .status()
+    .map_err(|e| format!("Error: Failed to run `{command}`\n{e}"))?;
+  if status.success() {
+    Ok(())
+  } else {
+    Err(format!("`{command}` exited with {status}"))
+  }
+}
+
+fn run_steps(config: &WatchConfig, config_dir: &Path) {
+  let mut summary = Vec::new();
+  let mut failed = false;
+  for &step in &config.steps {
+    if failed {
+      summary.push((step.name(), "skipped"));
+      continue;
+    }
+    println!("\n== {} ==", step.name());
+    let result = match step {
+      WatchStep::Format => format_file(config.source.clone(), None),
+      WatchStep::Check => check_file(config.source.clone(), false),
+      WatchStep::Compile => {
+        compile_once(&config.source, &config.output, false, Emit::Wgsl, &None)
+      }
+    };
+    if let Err(e) = result {
+      eprintln!("{e}");
+      failed = true;
+    }
+    summary.push((step.name(), if failed { "❌" } else { "✅" }));
+  }
+
+  if let Some(command) = &config.exec {
+    if failed {
+      summary.push(("exec", "skipped"));
+    } else {
+      println!("\n== exec ==");
+      let result = run_exec_hook(command, config_dir);
+      if let Err(e) = &result {
+        eprintln!("{e}");
+      }
+      summary.push(("exec", if result.is_ok() { "✅" } else { "❌" }));
+    }
+  }
+
+  println!(
+    "\n{}",
+    summary
+      .iter()
+      .map(|(step, status)| format!("{step} {status}"))
+      .collect::<Vec<_>>()
+      .join("   ")
+  );
+}
+
+fn read_easl_files(source: &PathBuf) -> HashMap<PathBuf, String> {
+  find_easl_files(source)
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|file| {
+      let content = fs::read_to_string(&file).ok()?;
+      Some((file, content))
+    })
+    .collect()
+}
+
+pub fn watch_project(config_path: PathBuf) -> Result<(), String> {
+  let config = load_config(&config_path)?;
+  let config_dir = config_dir(&config_path);
+  if !config.source.is_dir() {
+    return Err(format!(
+      "Error: Source directory {} does not exist",
+      config.source.display()
+    ));
+  }
+
+  run_steps(&config, config_dir);
+  // Cached after the steps run, so that writes made by the format step
+  // don't trigger another run.
+  let mut file_contents = read_easl_files(&config.source);
+
+  println!("\nWatching for changes... (Press Ctrl+C to stop)");
+
+  let (tx, rx) = channel();
+  let mut watcher = RecommendedWatcher::new(tx, Config::default())
+    .map_err(|e| format!("Error: Failed to create file watcher\n{}", e))?;
+  watcher
+    .watch(&config.source, RecursiveMode::Recursive)
+    .map_err(|e| {
+      format!(
+        "Error: Failed to watch path {}\n{}",
+        config.source.display(),
+        e
+      )
+    })?;
+
+  loop {
+    match rx.recv() {
+      Ok(Ok(Event {
+        kind: EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_),
+        paths,
+        ..
+      })) => {
+        let touches_easl = paths.iter().any(|path| {
+          path.extension().and_then(|s| s.to_str()) == Some("easl")
+        });
+        if touches_easl && read_easl_files(&config.source) != file_contents {
+          run_steps(&config, config_dir);
+          file_contents = read_easl_files(&config.source);
+        }
+      }
+      Ok(Ok(_)) => {}
+      Ok(Err(e)) => eprintln!("Watch error: {}", e),
+      Err(e) => {
+        return Err(format!("Error: Channel receive error\n{}", e));
+      }
+    }
+  }
+}